@@ -0,0 +1,428 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use der::DecodePem;
+use ecdsa::SigningKey;
+use k8s_openapi::api::certificates::v1alpha1::PodCertificateRequest;
+use p256::NistP256;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use signature::Signer;
+use spki::SubjectPublicKeyInfoOwned;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+use x509_cert::serial_number::SerialNumber;
+
+use crate::{Error, issuer::Issuer};
+
+/// how long to wait after notifying the ACME server that a challenge is ready for validation
+/// before the first status poll
+const CHALLENGE_PROPAGATION_DELAY: Duration = Duration::from_secs(5);
+/// how many times an order or authorization is polled before issuance is given up on
+const POLL_ATTEMPTS: usize = 10;
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// an [`Issuer`] that obtains pod leaf certificates from a public ACME (RFC 8555) CA instead of
+/// an OpenBao intermediate, for operators who already front their cluster with one. the ACME
+/// account is registered lazily on first use and cached for the lifetime of the controller,
+/// mirroring how [`crate::intermediate_ca::IntermediateCA`] defers issuing its own CA certificate
+/// until it is first needed.
+///
+/// EXPERIMENTAL: this cannot yet complete a real issuance. [`build_csr`] cannot produce a
+/// compliant, self-signed PKCS#10 CSR because this controller never holds the pod's private key,
+/// so it sends the bare SPKI in its place, which a compliant server rejects; and
+/// [`Self::satisfy_authorization`] cannot provision a DNS-01/HTTP-01 challenge itself, so it logs
+/// the key authorization an external solver would need and proceeds. only reachable via the explicit
+/// `ISSUER_BACKEND=acme-experimental` opt-in in `main.rs`, not the plain `acme` value.
+pub(crate) struct AcmeIssuer {
+    http: reqwest::Client,
+    directory_url: String,
+    directory: RwLock<Option<Directory>>,
+    account: RwLock<Option<AcmeAccount>>,
+}
+
+struct AcmeAccount {
+    key: SigningKey<NistP256>,
+    kid: String,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Deserialize)]
+struct Order {
+    status: String,
+    authorizations: Vec<String>,
+    finalize: String,
+    certificate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Authorization {
+    challenges: Vec<Challenge>,
+}
+
+#[derive(Deserialize, Clone)]
+struct Challenge {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    token: String,
+}
+
+impl AcmeIssuer {
+    pub fn new(directory_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            directory_url: directory_url.into(),
+            directory: RwLock::new(None),
+            account: RwLock::new(None),
+        }
+    }
+
+    async fn directory(&self) -> Result<Directory, Error> {
+        if let Some(directory) = self.directory.read().await.as_ref() {
+            return Ok(directory.clone());
+        }
+
+        let directory: Directory = self
+            .http
+            .get(&self.directory_url)
+            .send()
+            .await
+            .map_err(|e| Error::Signing(format!("failed to fetch ACME directory: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| Error::Signing(format!("failed to parse ACME directory: {}", e)))?;
+
+        self.directory.write().await.replace(directory.clone());
+        Ok(directory)
+    }
+
+    async fn nonce(&self, directory: &Directory) -> Result<String, Error> {
+        let response = self
+            .http
+            .head(&directory.new_nonce)
+            .send()
+            .await
+            .map_err(|e| Error::Signing(format!("failed to fetch ACME nonce: {}", e)))?;
+
+        response
+            .headers()
+            .get("replay-nonce")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+            .ok_or_else(|| Error::Signing("ACME server did not return a nonce".to_string()))
+    }
+
+    /// register (or, if one already exists for this account key, look up) an ACME account,
+    /// caching the account key and its server-assigned `kid` for subsequent requests
+    async fn account(&self) -> Result<Arc<AcmeAccount>, Error> {
+        if let Some(account) = self.account.read().await.as_ref() {
+            return Ok(Arc::new(AcmeAccount {
+                key: account.key.clone(),
+                kid: account.kid.clone(),
+            }));
+        }
+
+        let directory = self.directory().await?;
+        let key = SigningKey::<NistP256>::random(&mut rand::thread_rng());
+
+        let payload = json!({ "termsOfServiceAgreed": true });
+        let (response, kid) = self
+            .signed_request_with_jwk(&directory, &key, &directory.new_account, &payload)
+            .await?;
+        let kid = kid.ok_or_else(|| {
+            Error::Signing("ACME server did not return an account URL".to_string())
+        })?;
+        drop(response);
+
+        info!("registered ACME account {}", kid);
+        let account = AcmeAccount {
+            key: key.clone(),
+            kid: kid.clone(),
+        };
+        self.account.write().await.replace(AcmeAccount { key, kid });
+        Ok(Arc::new(account))
+    }
+
+    /// sign and POST a JWS request authenticated by the account's public key itself, as used for
+    /// the `newAccount` request before a `kid` has been assigned. returns the response body and,
+    /// if present, the `Location` header (the account URL / `kid`).
+    async fn signed_request_with_jwk(
+        &self,
+        directory: &Directory,
+        key: &SigningKey<NistP256>,
+        url: &str,
+        payload: &Value,
+    ) -> Result<(Value, Option<String>), Error> {
+        let jwk = jwk(key);
+        let protected = json!({
+            "alg": "ES256",
+            "jwk": jwk,
+            "nonce": self.nonce(directory).await?,
+            "url": url,
+        });
+        self.post(key, url, &protected, Some(payload)).await
+    }
+
+    /// sign and POST a JWS request authenticated by the account's `kid`, as used for every ACME
+    /// request after account registration
+    async fn signed_request(
+        &self,
+        account: &AcmeAccount,
+        directory: &Directory,
+        url: &str,
+        payload: Option<&Value>,
+    ) -> Result<(Value, Option<String>), Error> {
+        let protected = json!({
+            "alg": "ES256",
+            "kid": account.kid,
+            "nonce": self.nonce(directory).await?,
+            "url": url,
+        });
+        self.post(&account.key, url, &protected, payload).await
+    }
+
+    async fn post(
+        &self,
+        key: &SigningKey<NistP256>,
+        url: &str,
+        protected: &Value,
+        payload: Option<&Value>,
+    ) -> Result<(Value, Option<String>), Error> {
+        let protected_b64 = URL_SAFE_NO_PAD.encode(protected.to_string());
+        let payload_b64 = match payload {
+            Some(payload) => URL_SAFE_NO_PAD.encode(payload.to_string()),
+            // a POST-as-GET request has an explicitly empty payload, not an absent one
+            None => String::new(),
+        };
+
+        let signing_input = format!("{}.{}", protected_b64, payload_b64);
+        let signature: p256::ecdsa::Signature = key.sign(signing_input.as_bytes());
+        let signature_b64 = URL_SAFE_NO_PAD.encode(signature.to_bytes());
+
+        let body = json!({
+            "protected": protected_b64,
+            "payload": payload_b64,
+            "signature": signature_b64,
+        });
+
+        let response = self
+            .http
+            .post(url)
+            .header("content-type", "application/jose+json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| Error::Signing(format!("ACME request to {} failed: {}", url, e)))?
+            .error_for_status()
+            .map_err(|e| Error::Signing(format!("ACME server returned an error: {}", e)))?;
+
+        let location = response
+            .headers()
+            .get("location")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+
+        let text = response
+            .text()
+            .await
+            .map_err(|e| Error::Signing(format!("failed to read ACME response: {}", e)))?;
+        // certificate downloads are PEM rather than JSON, so a non-JSON body is returned as a string
+        let body: Value = serde_json::from_str(&text).unwrap_or(Value::String(text));
+
+        Ok((body, location))
+    }
+
+    /// take the first challenge the authorization offers, log the key authorization it needs
+    /// (provisioning it is left to an external solver), notify the ACME server it is ready to be
+    /// validated, and poll until the authorization is valid
+    async fn satisfy_authorization(
+        &self,
+        account: &AcmeAccount,
+        directory: &Directory,
+        authorization_url: &str,
+    ) -> Result<(), Error> {
+        let (authorization, _) = self
+            .signed_request(account, directory, authorization_url, None)
+            .await?;
+        let authorization: Authorization = serde_json::from_value(authorization)
+            .map_err(|e| Error::Signing(format!("invalid ACME authorization: {}", e)))?;
+
+        let challenge = authorization
+            .challenges
+            .first()
+            .ok_or_else(|| Error::Signing("ACME authorization has no challenges".to_string()))?;
+
+        // the key authorization is `token || '.' || base64url(jwk thumbprint)`; for a dns-01
+        // challenge its base64url SHA-256 digest would be published as a TXT record at
+        // `_acme-challenge.<domain>`, for http-01 it is served at `/.well-known/acme-challenge/<token>`.
+        // this controller has no way to provision either from inside a pod, so it logs the
+        // material an external solver (e.g. a DNS-01 webhook) would need and proceeds optimistically.
+        let key_authorization = format!(
+            "{}.{}",
+            challenge.token,
+            URL_SAFE_NO_PAD.encode(jwk_thumbprint(&account.key)?)
+        );
+        warn!(
+            "ACME {} challenge requires out-of-band provisioning of key authorization {}; \
+             this controller does not automate that step",
+            challenge.kind, key_authorization
+        );
+
+        self.signed_request(account, directory, &challenge.url, Some(&json!({})))
+            .await?;
+
+        tokio::time::sleep(CHALLENGE_PROPAGATION_DELAY).await;
+
+        for _ in 0..POLL_ATTEMPTS {
+            let (authorization, _) = self
+                .signed_request(account, directory, authorization_url, None)
+                .await?;
+            if authorization.get("status").and_then(Value::as_str) == Some("valid") {
+                return Ok(());
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        Err(Error::Signing(
+            "timed out waiting for ACME authorization to become valid".to_string(),
+        ))
+    }
+}
+
+/// the ES256 JWK representation (RFC 7518 section 6.2.1) of a P-256 public key, as embedded in
+/// the `newAccount` request and used to compute the key authorization thumbprint (RFC 7638)
+fn jwk(key: &SigningKey<NistP256>) -> Value {
+    let point = key.verifying_key().to_encoded_point(false);
+    json!({
+        "kty": "EC",
+        "crv": "P-256",
+        "x": URL_SAFE_NO_PAD.encode(point.x().expect("uncompressed point has an x coordinate")),
+        "y": URL_SAFE_NO_PAD.encode(point.y().expect("uncompressed point has a y coordinate")),
+    })
+}
+
+fn jwk_thumbprint(key: &SigningKey<NistP256>) -> Result<Vec<u8>, Error> {
+    // RFC 7638: the thumbprint is the hash of the JWK's required members, lexicographically
+    // ordered, with no insignificant whitespace
+    let jwk = jwk(key);
+    let canonical = format!(
+        "{{\"crv\":\"P-256\",\"kty\":\"EC\",\"x\":\"{}\",\"y\":\"{}\"}}",
+        jwk["x"].as_str().unwrap(),
+        jwk["y"].as_str().unwrap()
+    );
+    Ok(Sha256::digest(canonical.as_bytes()).to_vec())
+}
+
+#[async_trait]
+impl Issuer for AcmeIssuer {
+    async fn sign_certificate(
+        &self,
+        request: &PodCertificateRequest,
+    ) -> Result<x509_cert::Certificate, Error> {
+        let namespace = request.metadata.namespace.as_deref().unwrap_or("default");
+        let pod_name = &request.spec.pod_name;
+        let dns_name = format!("{}.{}.pod.svc.cluster.local", pod_name, namespace);
+
+        debug!("requesting ACME certificate for {}", dns_name);
+
+        let directory = self.directory().await?;
+        let account = self.account().await?;
+
+        let (order, order_url) = self
+            .signed_request(
+                &account,
+                &directory,
+                &directory.new_order,
+                Some(&json!({ "identifiers": [{ "type": "dns", "value": dns_name }] })),
+            )
+            .await?;
+        let order_url = order_url
+            .ok_or_else(|| Error::Signing("ACME server did not return an order URL".to_string()))?;
+        let order: Order = serde_json::from_value(order)
+            .map_err(|e| Error::Signing(format!("invalid ACME order: {}", e)))?;
+
+        for authorization_url in &order.authorizations {
+            self.satisfy_authorization(&account, &directory, authorization_url)
+                .await?;
+        }
+
+        let subject_public_key =
+            SubjectPublicKeyInfoOwned::from_der(request.spec.pkix_public_key.0.as_slice())
+                .map_err(Error::Der)?;
+        let csr = build_csr(&subject_public_key)?;
+
+        self.signed_request(
+            &account,
+            &directory,
+            &order.finalize,
+            Some(&json!({ "csr": URL_SAFE_NO_PAD.encode(csr) })),
+        )
+        .await?;
+
+        for _ in 0..POLL_ATTEMPTS {
+            let (order, _) = self
+                .signed_request(&account, &directory, &order_url, None)
+                .await?;
+            let order: Order = serde_json::from_value(order)
+                .map_err(|e| Error::Signing(format!("invalid ACME order: {}", e)))?;
+
+            match order.status.as_str() {
+                "valid" => {
+                    let certificate_url = order.certificate.ok_or_else(|| {
+                        Error::Signing("valid ACME order has no certificate URL".to_string())
+                    })?;
+                    let (chain_pem, _) = self
+                        .signed_request(&account, &directory, &certificate_url, None)
+                        .await?;
+                    let chain_pem = chain_pem
+                        .as_str()
+                        .ok_or_else(|| Error::Signing("ACME certificate response was not text".to_string()))?;
+                    return x509_cert::Certificate::from_pem(chain_pem).map_err(Error::Der);
+                }
+                "invalid" => {
+                    return Err(Error::Signing(
+                        "ACME order failed validation".to_string(),
+                    ));
+                }
+                _ => tokio::time::sleep(POLL_INTERVAL).await,
+            }
+        }
+
+        Err(Error::Signing(
+            "timed out waiting for ACME order to finalize".to_string(),
+        ))
+    }
+
+    async fn revoke_certificate(
+        &self,
+        _serial_number: &SerialNumber,
+        _reason: crate::revocation::RevocationReason,
+    ) -> Result<(), Error> {
+        // the issuing ACME CA owns revocation and its own CRL/OCSP responder; this controller
+        // has no local revocation state to update for certificates it didn't issue itself
+        warn!("revocation is not supported for the ACME issuer backend");
+        Ok(())
+    }
+}
+
+/// a PKCS#10 CSR must be self-signed by the private key matching its subjectPublicKeyInfo, to
+/// prove possession of that key. every other issuance path in this controller works directly
+/// from a pod's public key (see `utils::sign_certificate`) precisely to avoid needing that
+/// private key, which this controller never has access to - so a real CSR cannot be produced
+/// here. this placeholder ships the bare SPKI as the CSR body; a compliant ACME server will
+/// reject `finalize` with a `badCSR` error, which surfaces through `sign_certificate`'s normal
+/// error path as an `Error::Signing`. using ACME for pod certificates therefore requires either
+/// giving this controller the pod's private key, or having pods submit the CSR themselves.
+fn build_csr(subject_public_key: &SubjectPublicKeyInfoOwned) -> Result<Vec<u8>, Error> {
+    subject_public_key.to_der().map_err(Error::Der)
+}
@@ -19,13 +19,35 @@ impl From<(KeyPair, SignIntermediateResponse)> for CACertificate {
 }
 
 impl CACertificate {
-    pub fn is_expired(&self) -> bool {
+    fn validity(&self) -> Option<x509_cert::time::Validity> {
         use der::DecodePem;
-        let cert = match x509_cert::Certificate::from_pem(&self.certificate_pem) {
-            Ok(cert) => cert,
-            Err(_) => return true,
+        x509_cert::Certificate::from_pem(&self.certificate_pem)
+            .ok()
+            .map(|cert| cert.tbs_certificate.validity)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self.validity() {
+            Some(validity) => validity.not_after.to_system_time() < SystemTime::now(),
+            None => true,
+        }
+    }
+
+    /// true once `renew_fraction` of the certificate's total lifetime remains before
+    /// `not_after`, i.e. we have entered the proactive renewal window
+    pub fn needs_renewal(&self, renew_fraction: f64) -> bool {
+        let Some(validity) = self.validity() else {
+            return true;
+        };
+        let not_before = validity.not_before.to_system_time();
+        let not_after = validity.not_after.to_system_time();
+        let Ok(lifetime) = not_after.duration_since(not_before) else {
+            return true;
         };
+        let renew_at = not_after
+            .checked_sub(lifetime.mul_f64(renew_fraction))
+            .unwrap_or(not_before);
 
-        cert.tbs_certificate.validity.not_after.to_system_time() < SystemTime::now()
+        SystemTime::now() >= renew_at
     }
 }
@@ -0,0 +1,328 @@
+use std::time::Duration;
+
+use const_oid::AssociatedOid;
+use der::{
+    Decode, Encode,
+    asn1::{Null, ObjectIdentifier, OctetString},
+};
+use p256::ecdsa::{DerSignature, VerifyingKey};
+use pkcs8::DecodePublicKey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use signature::Verifier;
+use tracing::{debug, warn};
+use x509_cert::{
+    ext::{AsExtension, Extensions},
+    name::Name,
+};
+
+use crate::Error;
+
+/// configuration for submitting issued leaf certificates to a Certificate Transparency log
+#[derive(Clone)]
+pub(crate) struct CtLogConfig {
+    /// base URL of the CT log, e.g. `https://ct.example.com/logs/my-log`
+    pub log_url: String,
+    /// the log's DER-encoded SubjectPublicKeyInfo, used to verify the signature on every SCT it
+    /// returns before embedding it, so a party that can merely answer on `log_url` cannot inject
+    /// an arbitrary SCT into issued certificates
+    pub log_public_key: Vec<u8>,
+}
+
+/// the CT "poison" extension (RFC 6962 section 3.1) added to a precertificate before it is
+/// signed, so a precertificate can never be mistaken for (or chain as) a final certificate
+#[derive(Clone)]
+pub(crate) struct PoisonExtension(Null);
+
+impl PoisonExtension {
+    pub fn new() -> Self {
+        Self(Null)
+    }
+}
+
+impl Default for PoisonExtension {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AssociatedOid for PoisonExtension {
+    const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.11129.2.4.3");
+}
+
+impl Encode for PoisonExtension {
+    fn encoded_len(&self) -> der::Result<der::Length> {
+        self.0.encoded_len()
+    }
+
+    fn encode(&self, writer: &mut impl der::Writer) -> der::Result<()> {
+        self.0.encode(writer)
+    }
+}
+
+impl AsExtension for PoisonExtension {
+    fn critical(&self, _subject: &Name, _extensions: &Extensions) -> bool {
+        true
+    }
+}
+
+/// the embedded SCT list extension (RFC 6962 section 3.3), added to the final certificate once
+/// the log's SCT(s) for the matching precertificate have been collected
+#[derive(Clone)]
+pub(crate) struct SctListExtension(OctetString);
+
+impl AssociatedOid for SctListExtension {
+    const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.11129.2.4.2");
+}
+
+impl Encode for SctListExtension {
+    fn encoded_len(&self) -> der::Result<der::Length> {
+        self.0.encoded_len()
+    }
+
+    fn encode(&self, writer: &mut impl der::Writer) -> der::Result<()> {
+        self.0.encode(writer)
+    }
+}
+
+impl AsExtension for SctListExtension {
+    fn critical(&self, _subject: &Name, _extensions: &Extensions) -> bool {
+        false
+    }
+}
+
+/// a single RFC 6962 `SignedCertificateTimestamp`, as returned by a log's `add-pre-chain` API
+struct SignedCertificateTimestamp {
+    version: u8,
+    log_id: [u8; 32],
+    timestamp: u64,
+    extensions: Vec<u8>,
+    /// the TLS-encoded `digitally-signed` struct (hash alg, sig alg, length, signature bytes),
+    /// taken verbatim from the log's response
+    signature: Vec<u8>,
+}
+
+impl SignedCertificateTimestamp {
+    /// TLS (RFC 5246 section 4) serialization of the SCT, as embedded in an SCT list
+    fn to_tls_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(1 + 32 + 8 + 2 + self.extensions.len() + self.signature.len());
+        out.push(self.version);
+        out.extend_from_slice(&self.log_id);
+        out.extend_from_slice(&self.timestamp.to_be_bytes());
+        out.extend_from_slice(&(self.extensions.len() as u16).to_be_bytes());
+        out.extend_from_slice(&self.extensions);
+        out.extend_from_slice(&self.signature);
+        out
+    }
+}
+
+#[derive(Serialize)]
+struct AddPreChainRequest {
+    chain: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct AddPreChainResponse {
+    sct_version: u8,
+    id: String,
+    timestamp: u64,
+    extensions: String,
+    signature: String,
+}
+
+/// build the SCT list extension to embed in a final certificate by submitting its matching
+/// precertificate (plus issuing chain) to the configured CT log's `add-pre-chain` endpoint.
+/// fails soft: any error submitting to or parsing the response from the log is logged as a
+/// warning and results in `None`, so CT log availability never blocks certificate issuance.
+pub(crate) async fn fetch_sct_list_extension(
+    config: &CtLogConfig,
+    precert_der: &[u8],
+    issuer_der: &[u8],
+) -> Option<SctListExtension> {
+    match submit_precert(config, precert_der, issuer_der).await {
+        Ok(sct) => Some(build_sct_list_extension(&sct)),
+        Err(e) => {
+            warn!(
+                "Failed to submit precertificate to CT log {}, issuing without an SCT: {:?}",
+                config.log_url, e
+            );
+            None
+        }
+    }
+}
+
+async fn submit_precert(
+    config: &CtLogConfig,
+    precert_der: &[u8],
+    issuer_der: &[u8],
+) -> Result<SignedCertificateTimestamp, Error> {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| Error::Signing(format!("failed to build CT log HTTP client: {}", e)))?;
+
+    let url = format!(
+        "{}/ct/v1/add-pre-chain",
+        config.log_url.trim_end_matches('/')
+    );
+    let body = AddPreChainRequest {
+        chain: vec![STANDARD.encode(precert_der), STANDARD.encode(issuer_der)],
+    };
+
+    let response: AddPreChainResponse = client
+        .post(&url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| Error::Signing(format!("CT log request failed: {}", e)))?
+        .error_for_status()
+        .map_err(|e| Error::Signing(format!("CT log returned an error: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| Error::Signing(format!("failed to parse CT log response: {}", e)))?;
+
+    debug!("received SCT from CT log (id={})", response.id);
+
+    let log_id: [u8; 32] = STANDARD
+        .decode(&response.id)
+        .map_err(|e| Error::Signing(format!("invalid SCT log id: {}", e)))?
+        .try_into()
+        .map_err(|_| Error::Signing("CT log id must be 32 bytes".to_string()))?;
+
+    let sct = SignedCertificateTimestamp {
+        version: response.sct_version,
+        log_id,
+        timestamp: response.timestamp,
+        extensions: STANDARD
+            .decode(&response.extensions)
+            .map_err(|e| Error::Signing(format!("invalid SCT extensions: {}", e)))?,
+        signature: STANDARD
+            .decode(&response.signature)
+            .map_err(|e| Error::Signing(format!("invalid SCT signature: {}", e)))?,
+    };
+
+    verify_sct_signature(&sct, &config.log_public_key, precert_der, issuer_der)?;
+
+    Ok(sct)
+}
+
+/// verifies the log's signature over `sct` (RFC 6962 section 3.2's "digitally-signed" struct
+/// over a precert entry) against the log's known public key, before the SCT is trusted enough to
+/// embed in an issued certificate
+fn verify_sct_signature(
+    sct: &SignedCertificateTimestamp,
+    log_public_key_der: &[u8],
+    precert_der: &[u8],
+    issuer_der: &[u8],
+) -> Result<(), Error> {
+    let precert = x509_cert::Certificate::from_der(precert_der).map_err(Error::Der)?;
+    let issuer = x509_cert::Certificate::from_der(issuer_der).map_err(Error::Der)?;
+
+    // the signed entry covers the precert's TBSCertificate with the poison extension removed,
+    // since that extension only exists to stop the precert being mistaken for a final cert
+    let mut tbs = precert.tbs_certificate;
+    if let Some(extensions) = tbs.extensions.as_mut() {
+        extensions.retain(|ext| ext.extn_id != PoisonExtension::OID);
+    }
+    let tbs_der = tbs.to_der().map_err(Error::Der)?;
+
+    let issuer_key_der = issuer
+        .tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .map_err(Error::Der)?;
+    let issuer_key_hash = Sha256::digest(&issuer_key_der);
+
+    let mut signed_data = Vec::with_capacity(12 + 32 + 3 + tbs_der.len() + 2 + sct.extensions.len());
+    signed_data.push(sct.version);
+    signed_data.push(0); // signature_type = certificate_timestamp
+    signed_data.extend_from_slice(&sct.timestamp.to_be_bytes());
+    signed_data.extend_from_slice(&[0x00, 0x01]); // entry_type = precert_entry
+    signed_data.extend_from_slice(&issuer_key_hash);
+    signed_data.extend_from_slice(&(tbs_der.len() as u32).to_be_bytes()[1..]); // 3-byte length
+    signed_data.extend_from_slice(&tbs_der);
+    signed_data.extend_from_slice(&(sct.extensions.len() as u16).to_be_bytes());
+    signed_data.extend_from_slice(&sct.extensions);
+
+    // the "digitally-signed" struct is hash_alg(1) || sig_alg(1) || length(2) || signature
+    let signature_bytes = sct
+        .signature
+        .get(4..)
+        .ok_or_else(|| Error::Signing("SCT signature too short".to_string()))?;
+
+    let verifying_key = VerifyingKey::from_public_key_der(log_public_key_der)
+        .map_err(|e| Error::Signing(format!("invalid CT log public key: {}", e)))?;
+    let signature = DerSignature::try_from(signature_bytes)
+        .map_err(|e| Error::Signing(format!("invalid SCT signature encoding: {}", e)))?;
+
+    verifying_key
+        .verify(&signed_data, &signature)
+        .map_err(|e| Error::Signing(format!("SCT signature verification failed: {}", e)))
+}
+
+/// wrap a single SCT in a `SignedCertificateTimestampList` (RFC 6962 section 3.3): a TLS vector
+/// of one or more length-prefixed SCTs, itself length-prefixed
+fn build_sct_list_extension(sct: &SignedCertificateTimestamp) -> SctListExtension {
+    let sct_bytes = sct.to_tls_bytes();
+
+    let mut sct_list = Vec::with_capacity(2 + sct_bytes.len());
+    sct_list.extend_from_slice(&(sct_bytes.len() as u16).to_be_bytes());
+    sct_list.extend_from_slice(&sct_bytes);
+
+    let mut list = Vec::with_capacity(2 + sct_list.len());
+    list.extend_from_slice(&(sct_list.len() as u16).to_be_bytes());
+    list.extend_from_slice(&sct_list);
+
+    // the extension value itself is an OCTET STRING containing the TLS-encoded list above;
+    // AsExtension then wraps this type's DER encoding in the X.509 extnValue OCTET STRING,
+    // producing the doubly-nested OCTET STRING RFC 6962 specifies for this extension
+    SctListExtension(OctetString::new(list).expect("SCT list fits in an OCTET STRING"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_sct() -> SignedCertificateTimestamp {
+        SignedCertificateTimestamp {
+            version: 0,
+            log_id: [0x42; 32],
+            timestamp: 0x0102_0304_0506_0708,
+            extensions: vec![],
+            signature: vec![0x04, 0x03, 0x00, 0x02, 0xaa, 0xbb],
+        }
+    }
+
+    #[test]
+    fn to_tls_bytes_lays_out_the_sct_fields_in_order() {
+        let sct = sample_sct();
+        let bytes = sct.to_tls_bytes();
+
+        assert_eq!(bytes[0], sct.version);
+        assert_eq!(&bytes[1..33], &sct.log_id);
+        assert_eq!(&bytes[33..41], &sct.timestamp.to_be_bytes());
+        assert_eq!(&bytes[41..43], &[0x00, 0x00]); // extensions_length, no extensions
+        assert_eq!(&bytes[43..], &sct.signature);
+    }
+
+    #[test]
+    fn build_sct_list_extension_double_length_prefixes_the_sct() {
+        let sct = sample_sct();
+        let sct_bytes = sct.to_tls_bytes();
+
+        let extension = build_sct_list_extension(&sct);
+        let list = extension.0.as_bytes();
+
+        // outer TLS vector length covers the inner vector (its own 2-byte length plus the SCT)
+        let outer_len = u16::from_be_bytes([list[0], list[1]]) as usize;
+        assert_eq!(outer_len, 2 + sct_bytes.len());
+        assert_eq!(list.len(), 2 + outer_len);
+
+        // inner TLS vector length covers just the single SCT
+        let inner_len = u16::from_be_bytes([list[2], list[3]]) as usize;
+        assert_eq!(inner_len, sct_bytes.len());
+        assert_eq!(&list[4..], &sct_bytes[..]);
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use axum::{Router, extract::State, http::StatusCode, response::IntoResponse, routing::get};
+use tracing::warn;
+
+use crate::intermediate_ca::IntermediateCA;
+
+/// serves the CRL and CA chain over plain HTTP, so pods and other relying parties can fetch
+/// them without needing direct access to OpenBao
+pub(crate) async fn serve(ca: IntermediateCA, addr: &str) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/crl", get(get_crl))
+        .route("/ca-chain", get(get_ca_chain))
+        .with_state(Arc::new(ca));
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn get_crl(State(ca): State<Arc<IntermediateCA>>) -> impl IntoResponse {
+    match ca.build_crl().await {
+        Ok(crl_pem) => (
+            StatusCode::OK,
+            [("content-type", "application/pkix-crl")],
+            crl_pem,
+        )
+            .into_response(),
+        Err(e) => {
+            warn!("Failed to build CRL: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// serves the current intermediate CA certificate, plus the superseded one while it is still
+/// within its renewal grace period, so pods that cached a chain signed by the old CA can fetch
+/// something that still validates it
+async fn get_ca_chain(State(ca): State<Arc<IntermediateCA>>) -> impl IntoResponse {
+    match ca.ca_chain_pem().await {
+        Ok(chain_pem) => (
+            StatusCode::OK,
+            [("content-type", "application/x-pem-file")],
+            chain_pem,
+        )
+            .into_response(),
+        Err(e) => {
+            warn!("Failed to build CA chain: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -1,32 +1,105 @@
 use k8s_openapi::api::certificates::v1alpha1::PodCertificateRequest;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
+use async_trait::async_trait;
 use rcgen::{CertificateParams, KeyPair};
 use tracing::{debug, info, warn};
 use vaultrs::{api::pki::requests::SignIntermediateRequestBuilder, client::VaultClient};
 
-use crate::{Error, ca_certificate::CACertificate, utils::sign_certificate};
+use crate::{
+    Error,
+    ca_certificate::CACertificate,
+    ct::CtLogConfig,
+    issuer::Issuer,
+    revocation::{RevocationList, RevocationReason},
+    utils::sign_certificate,
+};
+
+/// how long a freshly (re-)issued intermediate CA certificate is valid for
+const CA_TTL: &str = "168h";
+/// proactive renewal kicks in once this fraction of the CA certificate's total lifetime is all
+/// that remains before `not_after`
+const CA_RENEW_FRACTION: f64 = 0.2;
+/// how long a superseded CA certificate is still retained after a renewal, so pods that
+/// received a chain signed by it moments earlier can still validate
+const CA_RENEWAL_GRACE_PERIOD: Duration = Duration::from_secs(3600);
+/// interval at which the background task checks whether the CA needs renewing
+const CA_RENEWAL_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// maps a `CA_KEY_ALGORITHM` value to the rcgen algorithm constant `IntermediateCA` generates its
+/// keypair for, so the intermediate CA's own key algorithm (not just the leaf dispatch in
+/// `utils::sign_leaf`) is actually configurable.
+///
+/// `rsa` is deliberately not accepted here: `KeyPair::generate_for` delegates key generation to
+/// `ring`, which cannot generate RSA keys, so requesting it would only fail later and more
+/// obscurely inside `request_ca_certificate`. the RSA branches of `utils::sign_leaf` and
+/// `revocation::sign_crl` convert the CA keypair's PKCS#8 DER with `RsaSigningKey::from_pkcs8_der`,
+/// so they are not reached with a CA keypair generated here.
+pub fn parse_ca_key_algorithm(name: &str) -> Result<&'static rcgen::SignatureAlgorithm, Error> {
+    match name {
+        "ecdsa-p256" => Ok(&rcgen::PKCS_ECDSA_P256_SHA256),
+        "ecdsa-p384" => Ok(&rcgen::PKCS_ECDSA_P384_SHA384),
+        "ed25519" => Ok(&rcgen::PKCS_ED25519),
+        other => Err(Error::UnsupportedAlgorithm(format!(
+            "unknown CA_KEY_ALGORITHM {:?} (expected one of ecdsa-p256, ecdsa-p384, ed25519)",
+            other
+        ))),
+    }
+}
 
 /// IntermediateCA is a client that implements managing its own in-memory CA based on an OpenBao
 /// root CA. it performs no actions on initialisation, instead it generates its CA once the first
-/// leaf certificate is consumed. refreshing the CA certificate is not currently supported.
+/// leaf certificate is consumed. once issued, it is renewed proactively in the background before
+/// it expires, and reactively if issuance is ever attempted against an already-expired CA.
 pub(crate) struct IntermediateCA {
-    bao: VaultClient,
+    bao: Arc<RwLock<VaultClient>>,
     ca: Arc<RwLock<Option<CACertificate>>>,
+    previous_ca: Arc<RwLock<Option<CACertificate>>>,
+    cluster_domain: Arc<str>,
+    ct_log: Option<Arc<CtLogConfig>>,
+    revocations: RevocationList,
+    ca_key_algorithm: &'static rcgen::SignatureAlgorithm,
+}
+
+impl Clone for IntermediateCA {
+    fn clone(&self) -> Self {
+        Self {
+            bao: self.bao.clone(),
+            ca: self.ca.clone(),
+            previous_ca: self.previous_ca.clone(),
+            cluster_domain: self.cluster_domain.clone(),
+            ct_log: self.ct_log.clone(),
+            revocations: self.revocations.clone(),
+            ca_key_algorithm: self.ca_key_algorithm,
+        }
+    }
 }
 
 impl IntermediateCA {
-    pub fn new(client: VaultClient) -> Self {
+    /// `client` is shared with the background Vault Kubernetes auth renewal task (see
+    /// `vault_auth::spawn_renewal_task`), which re-authenticates it in place ahead of its lease
+    /// expiring - hence the lock, rather than owning the client outright
+    pub fn new(
+        client: Arc<RwLock<VaultClient>>,
+        cluster_domain: impl Into<Arc<str>>,
+        ct_log: Option<CtLogConfig>,
+        ca_key_algorithm: &'static rcgen::SignatureAlgorithm,
+    ) -> Self {
         Self {
             bao: client,
             ca: Arc::new(RwLock::new(None)),
+            previous_ca: Arc::new(RwLock::new(None)),
+            cluster_domain: cluster_domain.into(),
+            ct_log: ct_log.map(Arc::new),
+            revocations: RevocationList::new(),
+            ca_key_algorithm,
         }
     }
 
-    async fn issue_ca_certificate(&self) -> Result<(), Error> {
-        debug!("generating CA KeyPair");
-        let ca_key_pair = KeyPair::generate().map_err(|e| {
+    async fn request_ca_certificate(&self) -> Result<CACertificate, Error> {
+        debug!("generating CA KeyPair ({:?})", self.ca_key_algorithm);
+        let ca_key_pair = KeyPair::generate_for(self.ca_key_algorithm).map_err(|e| {
             warn!("Failed to generate CA keypair: {:?}", e);
             Error::CSRCreate(e)
         })?;
@@ -53,10 +126,11 @@ impl IntermediateCA {
         })?;
 
         let mut request_options = SignIntermediateRequestBuilder::default();
-        request_options.ttl("168h");
+        request_options.ttl(CA_TTL);
 
+        let bao = self.bao.read().await;
         let intermediate = vaultrs::pki::cert::ca::sign_intermediate(
-            &self.bao,
+            &*bao,
             "pki",
             &csr_pem,
             &common_name,
@@ -70,16 +144,63 @@ impl IntermediateCA {
 
         info!("Intermediate CA certificate issued from Vault");
 
-        // replace own state
-        self.ca
-            .to_owned()
-            .write_owned()
-            .await
-            .replace((ca_key_pair, intermediate).into());
+        Ok((ca_key_pair, intermediate).into())
+    }
+
+    async fn issue_ca_certificate(&self) -> Result<(), Error> {
+        let cert = self.request_ca_certificate().await?;
+        self.ca.write().await.replace(cert);
+        Ok(())
+    }
+
+    /// re-issue the intermediate CA certificate and atomically swap it into place, retaining
+    /// the superseded certificate for `CA_RENEWAL_GRACE_PERIOD` so in-flight reconciles and
+    /// pods that validated against the old chain moments earlier are never disrupted
+    async fn renew_ca_certificate(&self) -> Result<(), Error> {
+        let new_cert = self.request_ca_certificate().await?;
+
+        let old_cert = self.ca.write().await.replace(new_cert);
+        info!("Intermediate CA certificate renewed");
+
+        if let Some(old_cert) = old_cert {
+            self.previous_ca.write().await.replace(old_cert);
+
+            let previous_ca = self.previous_ca.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(CA_RENEWAL_GRACE_PERIOD).await;
+                previous_ca.write().await.take();
+                debug!("dropped superseded intermediate CA certificate after grace period");
+            });
+        }
 
         Ok(())
     }
 
+    /// spawn a background task that proactively renews the intermediate CA ahead of expiry,
+    /// so leaf issuance never has to block on a synchronous Vault round-trip
+    pub fn spawn_renewal_task(&self) {
+        let this = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(CA_RENEWAL_CHECK_INTERVAL).await;
+
+                let needs_renewal = this
+                    .ca
+                    .read()
+                    .await
+                    .as_ref()
+                    .is_some_and(|cert| cert.needs_renewal(CA_RENEW_FRACTION));
+
+                if needs_renewal {
+                    info!("proactively renewing intermediate CA certificate");
+                    if let Err(e) = this.renew_ca_certificate().await {
+                        warn!("background intermediate CA renewal failed: {:?}", e);
+                    }
+                }
+            }
+        });
+    }
+
     pub async fn sign_certificate(
         &self,
         request: &PodCertificateRequest,
@@ -96,23 +217,86 @@ impl IntermediateCA {
             .as_ref()
             .is_some_and(|cert| cert.is_expired())
         {
-            // renew certificate
-            info!("renewing intermediate CA certificate");
-            todo!();
+            // the background renewal task should normally keep us ahead of expiry; this is
+            // the reactive fallback for when it hasn't (e.g. its renewal attempts kept failing)
+            info!("renewing expired intermediate CA certificate");
+            self.renew_ca_certificate().await?;
         }
 
         // sign leaf certificate
-        let public_key = &request.spec.pkix_public_key;
+        let ca = self.ca.read().await;
+        let ca_cert = ca.as_ref().unwrap();
+
+        sign_certificate(
+            request,
+            &self.cluster_domain,
+            &ca_cert.certificate_pem,
+            &ca_cert.key_pair,
+            self.ct_log.as_deref(),
+        )
+        .await
+    }
+
+    /// revoke a previously-issued leaf certificate by serial number, so it is included in the
+    /// next CRL built by [`Self::build_crl`]
+    pub async fn revoke_certificate(
+        &self,
+        serial_number: &x509_cert::serial_number::SerialNumber,
+        reason: RevocationReason,
+    ) -> Result<(), Error> {
+        self.revocations.revoke(serial_number, reason).await
+    }
+
+    /// the current intermediate CA certificate, followed by the superseded one if a renewal
+    /// happened within the last [`CA_RENEWAL_GRACE_PERIOD`], so pods that received a leaf chain
+    /// signed by the old CA moments earlier can still validate it against this chain
+    pub async fn ca_chain_pem(&self) -> Result<String, Error> {
+        if self.ca.read().await.is_none() {
+            info!("issuing intermediate CA certificate");
+            self.issue_ca_certificate().await?;
+        }
+
+        let ca = self.ca.read().await;
+        let mut chain = ca.as_ref().unwrap().certificate_pem.clone();
+
+        if let Some(previous) = self.previous_ca.read().await.as_ref() {
+            chain.push_str(&previous.certificate_pem);
+        }
+
+        Ok(chain)
+    }
+
+    /// build and sign a CRL covering all certificates revoked so far, issued by the currently
+    /// active intermediate CA
+    pub async fn build_crl(&self) -> Result<String, Error> {
+        if self.ca.read().await.is_none() {
+            info!("issuing intermediate CA certificate");
+            self.issue_ca_certificate().await?;
+        }
 
         let ca = self.ca.read().await;
         let ca_cert = ca.as_ref().unwrap();
 
-        let cn = format!(
-            "system:pod:{}:{}",
-            request.metadata.namespace.as_deref().unwrap_or("default"),
-            request.spec.pod_name
-        );
+        self.revocations
+            .build_crl(&ca_cert.certificate_pem, &ca_cert.key_pair)
+            .await
+    }
+}
+
+#[async_trait]
+impl Issuer for IntermediateCA {
+    async fn sign_certificate(
+        &self,
+        request: &PodCertificateRequest,
+    ) -> Result<x509_cert::Certificate, Error> {
+        self.sign_certificate(request).await
+    }
 
-        sign_certificate(public_key, &ca_cert.certificate_pem, &ca_cert.key_pair, &cn)
+    async fn revoke_certificate(
+        &self,
+        serial_number: &x509_cert::serial_number::SerialNumber,
+        reason: RevocationReason,
+    ) -> Result<(), Error> {
+        self.revoke_certificate(serial_number, reason).await
     }
 }
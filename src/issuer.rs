@@ -0,0 +1,26 @@
+use k8s_openapi::api::certificates::v1alpha1::PodCertificateRequest;
+use x509_cert::serial_number::SerialNumber;
+
+use crate::{Error, revocation::RevocationReason};
+
+/// abstracts how leaf certificates are actually issued, so the controller can run against either
+/// an OpenBao intermediate CA or a public ACME CA without `reconcile` needing to know which
+/// backend is in use
+#[async_trait::async_trait]
+pub(crate) trait Issuer: Send + Sync {
+    async fn sign_certificate(
+        &self,
+        request: &PodCertificateRequest,
+    ) -> Result<x509_cert::Certificate, Error>;
+
+    /// revoke a previously issued certificate, if this backend tracks its own revocation state.
+    /// backends that don't (e.g. ACME, where the issuing CA itself owns revocation) can leave
+    /// this as a no-op.
+    async fn revoke_certificate(
+        &self,
+        _serial_number: &SerialNumber,
+        _reason: RevocationReason,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
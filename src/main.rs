@@ -2,7 +2,7 @@ use std::{env, error::Error as StdError, io::BufRead, sync::Arc, time::Duration}
 
 use anyhow::Context;
 use chrono::Utc;
-use der::EncodePem;
+use der::{DecodePem, EncodePem};
 use futures::StreamExt;
 use k8s_openapi::{
     api::certificates::v1alpha1::PodCertificateRequest,
@@ -15,14 +15,28 @@ use kube::{
     runtime::{Config, Controller, controller::Action, watcher},
 };
 use thiserror::Error;
+use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 use vaultrs::client::{VaultClient, VaultClientSettingsBuilder};
 
-use crate::intermediate_ca::IntermediateCA;
+use crate::{
+    acme::AcmeIssuer, intermediate_ca::IntermediateCA, issuer::Issuer,
+    revocation::RevocationReason,
+};
 
+mod acme;
 mod ca_certificate;
+mod ct;
+mod http;
 mod intermediate_ca;
+mod issuer;
+mod revocation;
 mod utils;
+mod vault_auth;
+
+/// finalizer added to a `PodCertificateRequest` so its issued certificate is revoked before the
+/// object is actually removed from the API server
+const REVOKE_ON_DELETE_FINALIZER: &str = "openbao-pki-controller.io/revoke-on-delete";
 
 #[derive(Debug, Error)]
 enum Error {
@@ -38,6 +52,8 @@ enum Error {
     Der(#[source] der::Error),
     #[error("Failed to sign certificate: {0}")]
     Signing(String),
+    #[error("Unsupported signing algorithm: {0}")]
+    UnsupportedAlgorithm(String),
 }
 
 /// Controller triggers this whenever our main object or our children changed
@@ -48,6 +64,68 @@ async fn reconcile(pcr: Arc<PodCertificateRequest>, ctx: Arc<Data>) -> Result<Ac
 
     debug!("Reconciling PCR {} for pod {}", pcr_name, pod_uid);
 
+    let namespace = pcr
+        .metadata
+        .namespace
+        .as_ref()
+        .ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let pcrs = Api::<PodCertificateRequest>::namespaced(client.clone(), namespace);
+
+    if pcr.metadata.deletion_timestamp.is_some() {
+        info!("PCR {} is being deleted, revoking its certificate", pcr_name);
+        if let Some(certificate_chain) = pcr.status.as_ref().and_then(|s| s.certificate_chain.as_ref()) {
+            match x509_cert::Certificate::from_pem(certificate_chain) {
+                Ok(cert) => {
+                    ctx.ca
+                        .revoke_certificate(
+                            &cert.tbs_certificate.serial_number,
+                            RevocationReason::CessationOfOperation,
+                        )
+                        .await?;
+                }
+                Err(e) => warn!(
+                    "Failed to parse issued certificate for PCR {}, skipping revocation: {:?}",
+                    pcr_name, e
+                ),
+            }
+        }
+
+        let mut finalizers = pcr.metadata.finalizers.clone().unwrap_or_default();
+        finalizers.retain(|f| f != REVOKE_ON_DELETE_FINALIZER);
+        pcrs.patch(
+            pcr.metadata.name.as_ref().unwrap(),
+            &PatchParams::default(),
+            &Patch::Merge(json!({"metadata": {"finalizers": finalizers}})),
+        )
+        .await
+        .map_err(|e| {
+            warn!("Failed to remove finalizer from PCR {}: {:?}", pcr_name, e);
+            Error::ConfigMapCreationFailed(e)
+        })?;
+
+        return Ok(Action::await_change());
+    }
+
+    if !pcr
+        .metadata
+        .finalizers
+        .as_ref()
+        .is_some_and(|finalizers| finalizers.iter().any(|f| f == REVOKE_ON_DELETE_FINALIZER))
+    {
+        let mut finalizers = pcr.metadata.finalizers.clone().unwrap_or_default();
+        finalizers.push(REVOKE_ON_DELETE_FINALIZER.to_string());
+        pcrs.patch(
+            pcr.metadata.name.as_ref().unwrap(),
+            &PatchParams::default(),
+            &Patch::Merge(json!({"metadata": {"finalizers": finalizers}})),
+        )
+        .await
+        .map_err(|e| {
+            warn!("Failed to add finalizer to PCR {}: {:?}", pcr_name, e);
+            Error::ConfigMapCreationFailed(e)
+        })?;
+    }
+
     if let Some(status) = &pcr.status
         && status.certificate_chain.is_some()
     {
@@ -75,9 +153,14 @@ async fn reconcile(pcr: Arc<PodCertificateRequest>, ctx: Arc<Data>) -> Result<Ac
     let not_before = cert.tbs_certificate.validity.not_before.to_system_time();
     let not_after = cert.tbs_certificate.validity.not_after.to_system_time();
 
-    let renew_at = chrono::Utc::now()
-        + Duration::from_secs(pcr.spec.max_expiration_seconds.unwrap() as u64)
-        - Duration::from_secs(3600);
+    let validity_duration = pcr
+        .spec
+        .max_expiration_seconds
+        .and_then(|secs| u64::try_from(secs).ok())
+        .map(Duration::from_secs)
+        .unwrap_or(utils::DEFAULT_VALIDITY);
+
+    let renew_at = chrono::Utc::now() + validity_duration - Duration::from_secs(3600);
 
     let mut status = pcr.status.to_owned().unwrap_or_default().clone();
     status.certificate_chain = Some(cert.to_pem(der::pem::LineEnding::LF).map_err(|e| {
@@ -106,13 +189,6 @@ async fn reconcile(pcr: Arc<PodCertificateRequest>, ctx: Arc<Data>) -> Result<Ac
         type_: String::from("Issued"),
     }]);
 
-    let pcrs = Api::<PodCertificateRequest>::namespaced(
-        client.clone(),
-        pcr.metadata
-            .namespace
-            .as_ref()
-            .ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?,
-    );
     debug!(
         "Patching status for PodCertificateRequest {} (pod {})",
         pcr_name, pod_uid
@@ -144,7 +220,7 @@ fn error_policy(object: Arc<PodCertificateRequest>, error: &Error, _ctx: Arc<Dat
 
 struct Data {
     client: Client,
-    ca: IntermediateCA,
+    ca: Arc<dyn Issuer>,
 }
 
 // code mostly taken from the kube.rs example
@@ -167,28 +243,100 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
-    let mut settings = VaultClientSettingsBuilder::default();
+    let cluster_domain =
+        env::var("CLUSTER_DOMAIN").unwrap_or_else(|_| "cluster.local".to_string());
+
+    // limit the controller to running a maximum of two concurrent reconciliations
+    let config = Config::default().concurrency(2);
 
-    settings.address(env::var("BAO_ADDR").context("Please set BAO_ADDR")?);
+    // operators fronting their cluster with a public ACME CA can issue pod certs without
+    // running OpenBao at all by switching ISSUER_BACKEND; OpenBao remains the default.
+    // ACME support is experimental (see acme.rs: it cannot yet produce a self-signed CSR or
+    // automatically provision a DNS-01/HTTP-01 challenge), so selecting it requires the
+    // explicit "acme-experimental" value rather than a plain "acme" - a plain backend switch
+    // would imply it works as a drop-in alternative to openbao, which it does not
+    let issuer_backend = env::var("ISSUER_BACKEND").unwrap_or_else(|_| "openbao".to_string());
+    let ca: Arc<dyn Issuer> = match issuer_backend.as_str() {
+        "acme-experimental" => {
+            let directory_url =
+                env::var("ACME_DIRECTORY_URL").context("Please set ACME_DIRECTORY_URL")?;
+            warn!(
+                "using EXPERIMENTAL ACME issuer backend ({}): it cannot produce a compliant CSR \
+                 or provision challenges automatically, and issuance will fail against a \
+                 compliant ACME server - see acme.rs for details",
+                directory_url
+            );
+            Arc::new(AcmeIssuer::new(directory_url))
+        }
+        "acme" => {
+            anyhow::bail!(
+                "ISSUER_BACKEND=acme is not a working issuer yet (no real CSR or challenge \
+                 provisioning); set ISSUER_BACKEND=acme-experimental to opt in anyway"
+            );
+        }
+        other => {
+            if other != "openbao" {
+                warn!(
+                    "unknown ISSUER_BACKEND {:?}, defaulting to openbao",
+                    other
+                );
+            }
 
-    if let Ok(token) = env::var("BAO_TOKEN") {
-        settings.token(token);
-    } else {
-        // TODO: implement kubernetes authentication
-    }
+            let mut settings = VaultClientSettingsBuilder::default();
+            settings.address(env::var("BAO_ADDR").context("Please set BAO_ADDR")?);
 
-    let settings = settings.build()?;
-    let bao = VaultClient::new(settings)?;
+            let kubernetes_auth_role = env::var("BAO_KUBERNETES_AUTH_ROLE").ok();
+            if let Ok(token) = env::var("BAO_TOKEN") {
+                settings.token(token);
+            } else if kubernetes_auth_role.is_none() {
+                anyhow::bail!("Please set BAO_TOKEN or BAO_KUBERNETES_AUTH_ROLE");
+            }
 
-    let hostname = "";
-    let namespace = "";
-    let cluster_domain = "";
-    let _common_name = format!("{}.{}.svc.{}", hostname, namespace, cluster_domain);
+            let settings = settings.build()?;
+            let bao = Arc::new(RwLock::new(VaultClient::new(settings)?));
 
-    // limit the controller to running a maximum of two concurrent reconciliations
-    let config = Config::default().concurrency(2);
+            if let Some(role) = kubernetes_auth_role {
+                let mount = env::var("BAO_KUBERNETES_AUTH_MOUNT")
+                    .unwrap_or_else(|_| "kubernetes".to_string());
+                let lease_duration = vault_auth::login(&bao, &mount, &role).await?;
+                vault_auth::spawn_renewal_task(bao.clone(), mount, role, lease_duration);
+            }
+
+            let ct_log = match env::var("CT_LOG_URL") {
+                Ok(log_url) => {
+                    let log_public_key = base64::Engine::decode(
+                        &base64::engine::general_purpose::STANDARD,
+                        env::var("CT_LOG_PUBLIC_KEY")
+                            .context("CT_LOG_URL is set, please also set CT_LOG_PUBLIC_KEY (base64 DER SPKI) so its SCTs can be verified")?,
+                    )
+                    .context("CT_LOG_PUBLIC_KEY must be base64-encoded DER")?;
+                    Some(crate::ct::CtLogConfig {
+                        log_url,
+                        log_public_key,
+                    })
+                }
+                Err(_) => None,
+            };
+
+            let ca_key_algorithm = intermediate_ca::parse_ca_key_algorithm(
+                &env::var("CA_KEY_ALGORITHM").unwrap_or_else(|_| "ecdsa-p256".to_string()),
+            )?;
 
-    let ca = IntermediateCA::new(bao);
+            let ca = IntermediateCA::new(bao, cluster_domain.clone(), ct_log, ca_key_algorithm);
+            ca.spawn_renewal_task();
+
+            let crl_addr =
+                env::var("CRL_LISTEN_ADDR").unwrap_or_else(|_| "0.0.0.0:8080".to_string());
+            let crl_ca = ca.clone();
+            tokio::spawn(async move {
+                if let Err(e) = http::serve(crl_ca, &crl_addr).await {
+                    warn!("CRL HTTP server exited: {:?}", e);
+                }
+            });
+
+            Arc::new(ca)
+        }
+    };
 
     Controller::new(pcrs, watcher::Config::default())
         .with_config(config)
@@ -1,36 +1,67 @@
 use std::{str::FromStr, time::Duration};
 
-use der::{Decode, DecodePem};
+use const_oid::db::rfc5280::{ID_KP_CLIENT_AUTH, ID_KP_SERVER_AUTH};
+use der::{Decode, DecodePem, Encode, asn1::Ia5String};
 use ecdsa::SigningKey;
-use k8s_openapi::ByteString;
+use ed25519_dalek::{Signature as Ed25519Signature, SigningKey as Ed25519SigningKey};
+use k8s_openapi::api::certificates::v1alpha1::PodCertificateRequest;
 use p256::NistP256;
+use p384::NistP384;
 use pkcs8::DecodePrivateKey;
 use rcgen::KeyPair;
+use rsa::{pkcs1v15::SigningKey as RsaSigningKey, sha2::Sha256};
 use spki::SubjectPublicKeyInfoOwned;
 use tracing::{debug, warn};
 use x509_cert::{
     builder::{Builder, CertificateBuilder, Profile},
+    ext::pkix::{ExtendedKeyUsage, SubjectAltName, name::GeneralName},
     name::Name,
     serial_number::SerialNumber,
     time::Validity,
 };
 
-use crate::Error;
+use crate::{
+    Error,
+    ct::{CtLogConfig, PoisonExtension, SctListExtension, fetch_sct_list_extension},
+};
+
+/// leaf certificate lifetime used when the request does not specify `max_expiration_seconds`.
+/// also reused by `reconcile`'s `renew_at` computation, so the two stay consistent.
+pub(crate) const DEFAULT_VALIDITY: Duration = Duration::from_secs(86400);
 
-/// sign_certificate signs a certificate for a given pubkey using an intermediate CA certificate
-pub fn sign_certificate(
-    pubkey: &ByteString,
+/// sign_certificate signs a leaf certificate for the pod identified by `request`, honouring its
+/// requested lifetime and embedding the SAN / key usage extensions it needs to be usable for mTLS.
+/// the intermediate CA's signing algorithm is detected at runtime, so any RustCrypto-supported
+/// CA key (ECDSA P-256/P-384, Ed25519, RSA) can be used to issue the leaf.
+///
+/// when `ct_log` is configured, the leaf is first issued as a precertificate and submitted to
+/// the log so its SCT(s) can be embedded in the certificate that is actually returned; submission
+/// failures are logged and otherwise ignored, so CT log availability never blocks issuance.
+pub async fn sign_certificate(
+    request: &PodCertificateRequest,
+    cluster_domain: &str,
     ca_cert_pem: &str,
     ca_keypair: &KeyPair,
-    cn: &str,
+    ct_log: Option<&CtLogConfig>,
 ) -> Result<x509_cert::Certificate, Error> {
+    let namespace = request.metadata.namespace.as_deref().unwrap_or("default");
+    let pod_name = &request.spec.pod_name;
+    let service_account_name = &request.spec.service_account_name;
+    let cn = format!("system:pod:{}:{}", namespace, pod_name);
+
     debug!("Signing certificate for CN={}", cn);
 
     let subject_public_key =
-        SubjectPublicKeyInfoOwned::from_der(pubkey.0.as_slice()).map_err(|e| {
-            warn!("Failed to parse public key as SPKI: {:?}", e);
-            Error::Der(e)
-        })?;
+        SubjectPublicKeyInfoOwned::from_der(request.spec.pkix_public_key.0.as_slice()).map_err(
+            |e| {
+                warn!("Failed to parse public key as SPKI: {:?}", e);
+                Error::Der(e)
+            },
+        )?;
+    debug!(
+        "subject public key algorithm OID: {}",
+        subject_public_key.algorithm.oid
+    );
 
     let ca_cert = x509_cert::Certificate::from_pem(ca_cert_pem).map_err(|e| {
         warn!("Failed to parse CA certificate from PEM: {:?}", e);
@@ -46,37 +77,215 @@ pub fn sign_certificate(
     let subject = Name::from_str(&cn_formatted)
         .unwrap_or_else(|_| Name::from_str("CN=pod-certificate").unwrap());
     let serial_number = SerialNumber::from(u64::from_be_bytes(rand::random::<[u8; 8]>()));
-    let validity = Validity::from_now(Duration::from_secs(86400)).map_err(|e| {
+
+    let validity_duration = request
+        .spec
+        .max_expiration_seconds
+        .and_then(|secs| u64::try_from(secs).ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_VALIDITY);
+    let validity = Validity::from_now(validity_duration).map_err(|e| {
         warn!("Failed to create validity period: {:?}", e);
         Error::Der(e)
     })?;
-    let ca_key_der = ca_keypair.serialize_der();
-    let signing_key = SigningKey::<NistP256>::from_pkcs8_der(&ca_key_der).map_err(|e| {
-        warn!("Failed to convert CA keypair to ECDSA signing key: {:?}", e);
-        Error::Signing(format!("Key conversion failed: {}", e))
+
+    let profile = Profile::Leaf {
+        issuer: issuer_name,
+        enable_key_agreement: true,
+        enable_key_encipherment: true,
+    };
+
+    let san = SubjectAltName(pod_san_names(
+        pod_name,
+        service_account_name,
+        namespace,
+        cluster_domain,
+    )?);
+    let eku = ExtendedKeyUsage(vec![ID_KP_SERVER_AUTH, ID_KP_CLIENT_AUTH]);
+
+    let Some(ct_log) = ct_log else {
+        return sign_leaf(
+            ca_keypair,
+            profile,
+            serial_number,
+            validity,
+            subject,
+            subject_public_key,
+            &san,
+            &eku,
+            None,
+        );
+    };
+
+    let precert = sign_leaf(
+        ca_keypair,
+        profile.clone(),
+        serial_number,
+        validity,
+        subject.clone(),
+        subject_public_key.clone(),
+        &san,
+        &eku,
+        Some(LeafExtra::Poison(PoisonExtension::new())),
+    )?;
+    let precert_der = precert.to_der().map_err(|e| {
+        warn!("Failed to DER-encode precertificate: {:?}", e);
+        Error::Der(e)
     })?;
+    let issuer_der = ca_cert.to_der().map_err(|e| {
+        warn!("Failed to DER-encode issuer certificate: {:?}", e);
+        Error::Der(e)
+    })?;
+
+    let sct_list = fetch_sct_list_extension(ct_log, &precert_der, &issuer_der).await;
 
-    let builder = CertificateBuilder::new(
-        Profile::Leaf {
-            issuer: issuer_name,
-            enable_key_agreement: false,
-            enable_key_encipherment: false,
-        },
+    sign_leaf(
+        ca_keypair,
+        profile,
         serial_number,
         validity,
         subject,
         subject_public_key,
-        &signing_key,
+        &san,
+        &eku,
+        sct_list.map(LeafExtra::SctList),
     )
-    .map_err(|e| {
-        warn!("Failed to create certificate builder: {:?}", e);
-        Error::Signing(format!("Certificate builder creation failed: {}", e))
-    })?;
+}
 
-    let cert = builder.build::<p256::ecdsa::DerSignature>().map_err(|e| {
-        warn!("Failed to sign certificate: {:?}", e);
-        Error::Signing(format!("Certificate signing failed: {}", e))
+/// the one extension that differs between a precertificate and its matching final certificate
+enum LeafExtra {
+    Poison(PoisonExtension),
+    SctList(SctListExtension),
+}
+
+/// dispatches to the RustCrypto signer matching the intermediate CA's key algorithm, builds the
+/// leaf certificate against it, and signs it
+#[allow(clippy::too_many_arguments)]
+fn sign_leaf(
+    ca_keypair: &KeyPair,
+    profile: Profile,
+    serial_number: SerialNumber,
+    validity: Validity,
+    subject: Name,
+    subject_public_key: SubjectPublicKeyInfoOwned,
+    san: &SubjectAltName,
+    eku: &ExtendedKeyUsage,
+    extra: Option<LeafExtra>,
+) -> Result<x509_cert::Certificate, Error> {
+    let ca_key_der = ca_keypair.serialize_der();
+    let algorithm = ca_keypair.algorithm();
+
+    macro_rules! build_and_sign {
+        ($signer:expr, $signature:ty) => {{
+            let signer = $signer;
+            let mut builder = CertificateBuilder::new(
+                profile,
+                serial_number,
+                validity,
+                subject,
+                subject_public_key,
+                &signer,
+            )
+            .map_err(|e| {
+                warn!("Failed to create certificate builder: {:?}", e);
+                Error::Signing(format!("Certificate builder creation failed: {}", e))
+            })?;
+
+            builder.add_extension(san).map_err(|e| {
+                warn!("Failed to add SAN extension: {:?}", e);
+                Error::Signing(format!("Failed to add SAN extension: {}", e))
+            })?;
+            builder.add_extension(eku).map_err(|e| {
+                warn!("Failed to add extended key usage extension: {:?}", e);
+                Error::Signing(format!(
+                    "Failed to add extended key usage extension: {}",
+                    e
+                ))
+            })?;
+            match &extra {
+                Some(LeafExtra::Poison(poison)) => {
+                    builder.add_extension(poison).map_err(|e| {
+                        warn!("Failed to add CT poison extension: {:?}", e);
+                        Error::Signing(format!("Failed to add CT poison extension: {}", e))
+                    })?;
+                }
+                Some(LeafExtra::SctList(sct_list)) => {
+                    builder.add_extension(sct_list).map_err(|e| {
+                        warn!("Failed to add SCT list extension: {:?}", e);
+                        Error::Signing(format!("Failed to add SCT list extension: {}", e))
+                    })?;
+                }
+                None => {}
+            }
+
+            builder.build::<$signature>().map_err(|e| {
+                warn!("Failed to sign certificate: {:?}", e);
+                Error::Signing(format!("Certificate signing failed: {}", e))
+            })
+        }};
+    }
+
+    if *algorithm == rcgen::PKCS_ECDSA_P256_SHA256 {
+        let signer = SigningKey::<NistP256>::from_pkcs8_der(&ca_key_der).map_err(|e| {
+            warn!("Failed to convert CA keypair to ECDSA P-256 signing key: {:?}", e);
+            Error::Signing(format!("Key conversion failed: {}", e))
+        })?;
+        build_and_sign!(signer, p256::ecdsa::DerSignature)
+    } else if *algorithm == rcgen::PKCS_ECDSA_P384_SHA384 {
+        let signer = SigningKey::<NistP384>::from_pkcs8_der(&ca_key_der).map_err(|e| {
+            warn!("Failed to convert CA keypair to ECDSA P-384 signing key: {:?}", e);
+            Error::Signing(format!("Key conversion failed: {}", e))
+        })?;
+        build_and_sign!(signer, p384::ecdsa::DerSignature)
+    } else if *algorithm == rcgen::PKCS_ED25519 {
+        let signer = Ed25519SigningKey::from_pkcs8_der(&ca_key_der).map_err(|e| {
+            warn!("Failed to convert CA keypair to Ed25519 signing key: {:?}", e);
+            Error::Signing(format!("Key conversion failed: {}", e))
+        })?;
+        build_and_sign!(signer, Ed25519Signature)
+    } else if *algorithm == rcgen::PKCS_RSA_SHA256 {
+        let signer = RsaSigningKey::<Sha256>::from_pkcs8_der(&ca_key_der).map_err(|e| {
+            warn!("Failed to convert CA keypair to RSA signing key: {:?}", e);
+            Error::Signing(format!("Key conversion failed: {}", e))
+        })?;
+        build_and_sign!(signer, rsa::pkcs1v15::Signature)
+    } else {
+        Err(Error::UnsupportedAlgorithm(format!(
+            "CA key algorithm {:?} is not supported",
+            algorithm
+        )))
+    }
+}
+
+/// the SAN set a pod's leaf certificate carries: a DNS name for the pod itself and a SPIFFE URI
+/// identifying the service account it runs as.
+///
+/// no IP SAN is added: `PodCertificateRequestSpec` carries no pod-IP field to source one from
+/// (the pod IP isn't known yet when kubelet requests the cert), so there is nothing to derive it
+/// from here.
+fn pod_san_names(
+    pod_name: &str,
+    service_account_name: &str,
+    namespace: &str,
+    cluster_domain: &str,
+) -> Result<Vec<GeneralName>, Error> {
+    let dns_name = format!("{}.{}.pod.{}", pod_name, namespace, cluster_domain);
+    let spiffe_uri = format!(
+        "spiffe://{}/ns/{}/sa/{}",
+        cluster_domain, namespace, service_account_name
+    );
+
+    let dns_name = Ia5String::new(&dns_name).map_err(|e| {
+        warn!("Failed to encode SAN DNS name: {:?}", e);
+        Error::Der(e)
+    })?;
+    let spiffe_uri = Ia5String::new(&spiffe_uri).map_err(|e| {
+        warn!("Failed to encode SAN URI: {:?}", e);
+        Error::Der(e)
     })?;
 
-    Ok(cert)
+    Ok(vec![
+        GeneralName::DnsName(dns_name),
+        GeneralName::UniformResourceIdentifier(spiffe_uri),
+    ])
 }
@@ -0,0 +1,87 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use vaultrs::{
+    auth::kubernetes,
+    client::{Client, VaultClient},
+};
+
+use crate::Error;
+
+/// path the kubelet projects the pod's ServiceAccount token to
+const SERVICE_ACCOUNT_TOKEN_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+/// fraction of the returned lease TTL at which the background task proactively re-authenticates
+const RENEW_FRACTION: f64 = 0.5;
+/// interval at which the background task checks whether a re-login is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// authenticate to Vault via the Kubernetes auth method, using the pod's own projected
+/// ServiceAccount JWT, and store the returned client token on `client`. returns the lease
+/// duration of the issued token, so the caller can schedule a timely re-login.
+pub(crate) async fn login(
+    client: &Arc<RwLock<VaultClient>>,
+    mount: &str,
+    role: &str,
+) -> Result<Duration, Error> {
+    let jwt = read_service_account_token()?;
+
+    let mut guard = client.write().await;
+    let auth_info = kubernetes::login(&*guard, mount, role, &jwt)
+        .await
+        .map_err(|e| {
+            warn!("Kubernetes auth login to Vault failed: {:?}", e);
+            Error::VaultRequestFailed(e)
+        })?;
+
+    guard.set_token(&auth_info.client_token);
+    info!("authenticated to Vault via Kubernetes auth (role={})", role);
+
+    Ok(Duration::from_secs(auth_info.lease_duration))
+}
+
+fn read_service_account_token() -> Result<String, Error> {
+    std::fs::read_to_string(SERVICE_ACCOUNT_TOKEN_PATH)
+        .map(|token| token.trim().to_string())
+        .map_err(|e| {
+            warn!("Failed to read projected ServiceAccount token: {:?}", e);
+            Error::Signing(format!(
+                "failed to read projected ServiceAccount token: {}",
+                e
+            ))
+        })
+}
+
+/// spawn a background task that proactively re-authenticates to Vault ahead of the current
+/// token's lease expiring. the ServiceAccount JWT is re-read from disk on every login, so token
+/// rotation of the projected file (kubelet rotates it well ahead of its own expiry) is picked up
+/// transparently without a restart.
+pub(crate) fn spawn_renewal_task(
+    client: Arc<RwLock<VaultClient>>,
+    mount: String,
+    role: String,
+    initial_lease_duration: Duration,
+) {
+    tokio::spawn(async move {
+        let mut next_login_in = initial_lease_duration.mul_f64(RENEW_FRACTION);
+
+        loop {
+            tokio::time::sleep(next_login_in.min(CHECK_INTERVAL)).await;
+            if next_login_in > CHECK_INTERVAL {
+                next_login_in -= CHECK_INTERVAL;
+                continue;
+            }
+
+            match login(&client, &mount, &role).await {
+                Ok(lease_duration) => next_login_in = lease_duration.mul_f64(RENEW_FRACTION),
+                Err(e) => {
+                    warn!(
+                        "background Vault Kubernetes auth re-login failed, retrying: {:?}",
+                        e
+                    );
+                    next_login_in = CHECK_INTERVAL;
+                }
+            }
+        }
+    });
+}